@@ -1,28 +1,54 @@
 use wasm_bindgen::prelude::*;
 use std::f64::consts::PI;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 // ---------------------------------------------------------------------------
 // Fractal iteration kernels
 // ---------------------------------------------------------------------------
+//
+// Each kernel returns `(escape_count, last_z_re, last_z_im, min_trap_dist)` so
+// callers can derive a continuous (smoothed) iteration value from the final
+// orbit value, or color by the orbit's closest approach to an orbit trap,
+// instead of only the integer escape count.
+//
+// `trap_type`: 0=point (distance to origin), 1=cross (`min(|re|, |im|)`),
+// 2=circle of radius `trap_radius`. The trap distance is tracked every step
+// whether or not orbit-trap coloring is selected, so the color-mapping block
+// can pick whichever coloring mode the caller asked for without re-running
+// the orbit.
 
 #[inline(always)]
-fn iterate_standard(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32) -> u32 {
+fn trap_dist(z_re: f64, z_im: f64, r2: f64, trap_type: u32, trap_radius: f64) -> f64 {
+    match trap_type {
+        1 => z_re.abs().min(z_im.abs()),
+        2 => (r2.sqrt() - trap_radius).abs(),
+        _ => r2.sqrt(),
+    }
+}
+
+#[inline(always)]
+fn iterate_standard(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32, trap_type: u32, trap_radius: f64) -> (u32, f64, f64, f64) {
+    let mut min_dist = f64::MAX;
     for i in 0..max_iter {
         let r2 = z_re * z_re + z_im * z_im;
-        if r2 > 4.0 { return i; }
+        min_dist = min_dist.min(trap_dist(z_re, z_im, r2, trap_type, trap_radius));
+        if r2 > 256.0 { return (i, z_re, z_im, min_dist); }
         let new_re = z_re * z_re - z_im * z_im + c_re;
         let new_im = 2.0 * z_re * z_im + c_im;
         z_re = new_re;
         z_im = new_im;
     }
-    max_iter
+    (max_iter, z_re, z_im, min_dist)
 }
 
 #[inline(always)]
-fn iterate_ship(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32) -> u32 {
+fn iterate_ship(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32, trap_type: u32, trap_radius: f64) -> (u32, f64, f64, f64) {
+    let mut min_dist = f64::MAX;
     for i in 0..max_iter {
         let r2 = z_re * z_re + z_im * z_im;
-        if r2 > 4.0 { return i; }
+        min_dist = min_dist.min(trap_dist(z_re, z_im, r2, trap_type, trap_radius));
+        if r2 > 256.0 { return (i, z_re, z_im, min_dist); }
         let are = z_re.abs();
         let aim = z_im.abs();
         let new_re = are * are - aim * aim + c_re;
@@ -30,47 +56,53 @@ fn iterate_ship(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u3
         z_re = new_re;
         z_im = new_im;
     }
-    max_iter
+    (max_iter, z_re, z_im, min_dist)
 }
 
 #[inline(always)]
-fn iterate_tricorn(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32) -> u32 {
+fn iterate_tricorn(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32, trap_type: u32, trap_radius: f64) -> (u32, f64, f64, f64) {
+    let mut min_dist = f64::MAX;
     for i in 0..max_iter {
         let r2 = z_re * z_re + z_im * z_im;
-        if r2 > 4.0 { return i; }
+        min_dist = min_dist.min(trap_dist(z_re, z_im, r2, trap_type, trap_radius));
+        if r2 > 256.0 { return (i, z_re, z_im, min_dist); }
         let new_re = z_re * z_re - z_im * z_im + c_re;
         let new_im = -2.0 * z_re * z_im + c_im;
         z_re = new_re;
         z_im = new_im;
     }
-    max_iter
+    (max_iter, z_re, z_im, min_dist)
 }
 
 #[inline(always)]
-fn iterate_celtic(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32) -> u32 {
+fn iterate_celtic(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32, trap_type: u32, trap_radius: f64) -> (u32, f64, f64, f64) {
+    let mut min_dist = f64::MAX;
     for i in 0..max_iter {
         let r2 = z_re * z_re + z_im * z_im;
-        if r2 > 4.0 { return i; }
+        min_dist = min_dist.min(trap_dist(z_re, z_im, r2, trap_type, trap_radius));
+        if r2 > 256.0 { return (i, z_re, z_im, min_dist); }
         let are = z_re.abs();
         let new_re = are * are - z_im * z_im + c_re;
         let new_im = 2.0 * are * z_im + c_im;
         z_re = new_re;
         z_im = new_im;
     }
-    max_iter
+    (max_iter, z_re, z_im, min_dist)
 }
 
 #[inline(always)]
-fn iterate_cosine(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32) -> u32 {
+fn iterate_cosine(mut z_re: f64, mut z_im: f64, c_re: f64, c_im: f64, max_iter: u32, trap_type: u32, trap_radius: f64) -> (u32, f64, f64, f64) {
+    let mut min_dist = f64::MAX;
     for i in 0..max_iter {
         let r2 = z_re * z_re + z_im * z_im;
-        if r2 > 100.0 { return i; }
+        min_dist = min_dist.min(trap_dist(z_re, z_im, r2, trap_type, trap_radius));
+        if r2 > 100.0 { return (i, z_re, z_im, min_dist); }
         let new_re = z_re.cos() * z_im.cosh() + c_re;
         let new_im = -(z_re.sin()) * z_im.sinh() + c_im;
         z_re = new_re;
         z_im = new_im;
     }
-    max_iter
+    (max_iter, z_re, z_im, min_dist)
 }
 
 // ---------------------------------------------------------------------------
@@ -91,7 +123,74 @@ fn lerp_color(c1: (u8, u8, u8), c2: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
     )
 }
 
-fn build_gradient_lut(colors: &[(u8, u8, u8)], steps: usize) -> Vec<(u8, u8, u8)> {
+// --- Perceptual (OkLab) gradient interpolation --------------------------
+//
+// sRGB lerp blends in gamma-encoded space, which muddies mid-tones (e.g.
+// blue->yellow passes through gray). OkLab interpolation converts each stop
+// to a perceptually uniform space first, so multi-stop gradients stay clean.
+
+fn srgb_u8_to_linear(c: u8) -> f64 {
+    let cs = c as f64 / 255.0;
+    if cs > 0.04045 { ((cs + 0.055) / 1.055).powf(2.4) } else { cs / 12.92 }
+}
+
+fn linear_to_srgb_u8(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let cs = if c > 0.0031308 { 1.055 * c.powf(1.0 / 2.4) - 0.055 } else { c * 12.92 };
+    (cs * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Converts linear sRGB to OkLab (Björn Ottosson's published matrices).
+fn linear_rgb_to_oklab(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+    let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+    let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+    let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+
+    let l_ = l.cbrt();
+    let m_ = m.cbrt();
+    let s_ = s.cbrt();
+
+    (
+        0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+        1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+        0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+    )
+}
+
+/// Inverse of [`linear_rgb_to_oklab`].
+fn oklab_to_linear_rgb(l: f64, a: f64, b: f64) -> (f64, f64, f64) {
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    (
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    )
+}
+
+fn lerp_color_oklab(c1: (u8, u8, u8), c2: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+    let lin1 = (srgb_u8_to_linear(c1.0), srgb_u8_to_linear(c1.1), srgb_u8_to_linear(c1.2));
+    let lin2 = (srgb_u8_to_linear(c2.0), srgb_u8_to_linear(c2.1), srgb_u8_to_linear(c2.2));
+    let lab1 = linear_rgb_to_oklab(lin1.0, lin1.1, lin1.2);
+    let lab2 = linear_rgb_to_oklab(lin2.0, lin2.1, lin2.2);
+
+    let l = (1.0 - t) * lab1.0 + t * lab2.0;
+    let a = (1.0 - t) * lab1.1 + t * lab2.1;
+    let b = (1.0 - t) * lab1.2 + t * lab2.2;
+
+    let (r, g, bl) = oklab_to_linear_rgb(l, a, b);
+    (linear_to_srgb_u8(r), linear_to_srgb_u8(g), linear_to_srgb_u8(bl))
+}
+
+/// `gradient_space`: 0 = lerp in raw sRGB (current behavior), 1 = lerp in
+/// OkLab for a perceptually uniform, muddy-mid-tone-free gradient.
+fn build_gradient_lut(colors: &[(u8, u8, u8)], steps: usize, gradient_space: u32) -> Vec<(u8, u8, u8)> {
     let mut lut = vec![(0u8, 0u8, 0u8); steps];
     if colors.len() < 2 { return lut; }
     let n = colors.len() - 1;
@@ -102,20 +201,63 @@ fn build_gradient_lut(colors: &[(u8, u8, u8)], steps: usize) -> Vec<(u8, u8, u8)
         let seg = e - s;
         for j in 0..seg {
             let t = if seg > 1 { j as f64 / (seg - 1) as f64 } else { 0.0 };
-            lut[s + j] = lerp_color(colors[i], colors[i + 1], t);
+            lut[s + j] = if gradient_space == 1 {
+                lerp_color_oklab(colors[i], colors[i + 1], t)
+            } else {
+                lerp_color(colors[i], colors[i + 1], t)
+            };
         }
     }
     lut
 }
 
+/// Looks up a continuous (fractional) LUT index, interpolating between the
+/// two neighboring entries. `idx` wraps modulo `lut.len()`, matching the
+/// banded lookup's `% 1024` behavior.
+fn lerp_lut(lut: &[(u8, u8, u8)], idx: f64) -> (u8, u8, u8) {
+    let len = lut.len() as i64;
+    let floor = idx.floor();
+    let i0 = (floor as i64).rem_euclid(len) as usize;
+    let i1 = ((floor as i64) + 1).rem_euclid(len) as usize;
+    let frac = idx - floor;
+    lerp_color(lut[i0], lut[i1], frac)
+}
+
 // ---------------------------------------------------------------------------
 // Main render — exported to JavaScript
 // ---------------------------------------------------------------------------
 
-/// Renders a Julia Set fractal and returns an RGBA pixel buffer.
+/// Renders a Julia or Mandelbrot-family fractal and returns an RGBA pixel buffer.
 ///
 /// `colors_flat` is 15 bytes: 5 colors × 3 channels (R, G, B) packed sequentially.
 /// `fractal_type`: 0=standard, 1=ship, 2=tricorn, 3=celtic, 4=cosine
+/// `coloring_mode`: 0=banded (LUT index = iter*10 % 1024), 1=smooth (continuous
+/// iteration count derived from the escaped orbit, eliminating color banding),
+/// 2=histogram-equalized (LUT index derived from the cumulative distribution
+/// of escape counts across the image, so palette usage stays balanced
+/// regardless of zoom depth; interior points stay transparent/background).
+/// Smooth coloring falls back to the banded iteration count for `fractal_type`
+/// 4 (cosine), whose exponential escape makes the smoothing formula unstable.
+/// `aa_samples`: supersampling grid size per axis (1 = off, 2 = 2×2, 3 = 3×3);
+/// each output pixel averages `aa_samples²` jittered sub-samples.
+/// `set_mode`: 0=Julia (the pixel is `z0`, `c_re`/`c_im` are the fixed parameter),
+/// 1=Mandelbrot (the pixel is `c`, `z0` is fixed at the origin and `c_re`/`c_im`
+/// are ignored). Both modes reuse the same `iterate_*` kernels, so `fractal_type`
+/// 1-3 yield the Burning Ship / Mandelbar / Celtic parameter-space sets.
+/// `gradient_space`: 0=sRGB lerp (current behavior), 1=OkLab lerp (perceptual,
+/// avoids the muddy mid-tones raw sRGB interpolation produces).
+/// `coloring_mode` 3=orbit trap: colors by how close the orbit comes to a
+/// geometric trap rather than by escape speed, producing the organic "stalk"
+/// and "ring" imagery orbit traps are known for. `trap_type`: 0=point
+/// (distance to the origin), 1=cross (`min(|re|, |im|)`), 2=circle of radius
+/// `trap_radius`. Interior and exterior points both get a trap value, so
+/// unlike the other coloring modes, orbit-trap mode does not force interior
+/// points transparent/background.
+///
+/// Rows are independent (all per-pixel state is read-only after setup), so
+/// when built with the `parallel` feature (wasm-bindgen-rayon + a
+/// SharedArrayBuffer-backed thread pool) rendering is split across rows with
+/// `rayon::par_iter`; without the feature it falls back to the serial loop.
 #[wasm_bindgen]
 pub fn render(
     width: u32,
@@ -128,6 +270,11 @@ pub fn render(
     rotation_deg: f64,
     max_iter: u32,
     fractal_type: u32,
+    coloring_mode: u32,
+    set_mode: u32,
+    gradient_space: u32,
+    trap_type: u32,
+    trap_radius: f64,
     colors_flat: &[u8],  // 15 bytes: 5 colors × RGB
     bg_r: u8,
     bg_g: u8,
@@ -135,6 +282,7 @@ pub fn render(
     fade_black: f64,
     alpha_gamma: f64,
     transparent: bool,
+    aa_samples: u32,
 ) -> Vec<u8> {
     let w = width as usize;
     let h = height as usize;
@@ -147,7 +295,7 @@ pub fn render(
             grad_colors.push((colors_flat[off], colors_flat[off + 1], colors_flat[off + 2]));
         }
     }
-    let lut = build_gradient_lut(&grad_colors, 1024);
+    let lut = build_gradient_lut(&grad_colors, 1024, gradient_space);
 
     // Coordinate bounds
     let aspect = w as f64 / h as f64;
@@ -166,60 +314,227 @@ pub fn render(
     let cy = (min_y + max_y) / 2.0;
 
     // Select iteration function
-    let iterate: fn(f64, f64, f64, f64, u32) -> u32 = match fractal_type {
+    let iterate: fn(f64, f64, f64, f64, u32, u32, f64) -> (u32, f64, f64, f64) = match fractal_type {
         1 => iterate_ship,
         2 => iterate_tricorn,
         3 => iterate_celtic,
         4 => iterate_cosine,
         _ => iterate_standard,
     };
+    let smooth = coloring_mode == 1;
+    let histogram = coloring_mode == 2;
+    let orbit_trap = coloring_mode == 3;
+    let aa = aa_samples.max(1);
+    let step_x = (max_x - min_x) / w as f64;
+    let step_y = (max_y - min_y) / h as f64;
 
-    // Allocate output
-    let mut rgba = vec![0u8; w * h * 4];
+    // Runs the orbit for a single sample at the given (pre-rotation)
+    // fractal-space coordinate, honoring `set_mode`. Returns the escape
+    // count, the last `z` value, and the minimum orbit-trap distance seen
+    // (tracked unconditionally; only consulted when `coloring_mode` is 3).
+    let escape_at = |real_base: f64, imag_base: f64| -> (u32, f64, f64, f64) {
+        let dx = real_base - cx;
+        let dy = imag_base - cy;
+        let z_re = dx * cos_t - dy * sin_t + cx;
+        let z_im = dx * sin_t + dy * cos_t + cy;
 
-    for y in 0..h {
-        let imag_base = min_y + (y as f64 / h as f64) * (max_y - min_y);
-        for x in 0..w {
-            let real_base = min_x + (x as f64 / w as f64) * (max_x - min_x);
+        // In Mandelbrot mode the pixel is `c` and the orbit starts at the
+        // origin; in Julia mode the pixel is `z0` and `c` is fixed.
+        let (init_re, init_im, use_c_re, use_c_im) = if set_mode == 1 {
+            (0.0, 0.0, z_re, z_im)
+        } else {
+            (z_re, z_im, c_re, c_im)
+        };
+        iterate(init_re, init_im, use_c_re, use_c_im, max_iter, trap_type, trap_radius)
+    };
 
-            // Apply rotation
-            let dx = real_base - cx;
-            let dy = imag_base - cy;
-            let z_re = dx * cos_t - dy * sin_t + cx;
-            let z_im = dx * sin_t + dy * cos_t + cy;
+    // Turns a resolved (r, g, b, escape_count) into the final composited
+    // RGBA (as f64, 0..255), applying the brightness-driven alpha and
+    // background compositing shared by every coloring mode. Orbit-trap mode
+    // assigns every point (interior and exterior alike) a meaningful color,
+    // so it skips the "force interior transparent" rule the escape-based
+    // modes use.
+    let composite = |r: u8, g: u8, b: u8, iter: u32| -> (f64, f64, f64, f64) {
+        let brightness = r.max(g).max(b) as f64;
+        let alpha_norm = if fade_black >= 255.0 {
+            0.0
+        } else {
+            ((brightness - fade_black) / (255.0 - fade_black)).clamp(0.0, 1.0)
+        };
+        let alpha = alpha_norm.powf(alpha_gamma) * 255.0;
+        let interior = iter >= max_iter && !orbit_trap;
 
-            let iter = iterate(z_re, z_im, c_re, c_im, max_iter);
+        if transparent {
+            let a = if interior { 0.0 } else { alpha };
+            (r as f64, g as f64, b as f64, a)
+        } else {
+            let a_f = if interior { 0.0 } else { alpha / 255.0 };
+            (
+                r as f64 * a_f + bg_r as f64 * (1.0 - a_f),
+                g as f64 * a_f + bg_g as f64 * (1.0 - a_f),
+                b as f64 * a_f + bg_b as f64 * (1.0 - a_f),
+                255.0,
+            )
+        }
+    };
 
-            // Color mapping using LUT
-            let lut_idx = ((iter as usize).wrapping_mul(10)) % 1024;
-            let (r, g, b) = lut[lut_idx];
+    // Computes the fully composited RGBA (as f64, 0..255) for a single sample
+    // using the banded, smooth, or orbit-trap palette mapping. Called once
+    // per pixel when `aa == 1`, or `aa²` times (jittered within the pixel
+    // footprint) when supersampling is enabled.
+    let sample = |real_base: f64, imag_base: f64| -> (f64, f64, f64, f64) {
+        let (iter, esc_re, esc_im, min_dist) = escape_at(real_base, imag_base);
 
-            // Alpha from brightness
-            let brightness = r.max(g).max(b) as f64;
-            let alpha_norm = if fade_black >= 255.0 {
-                0.0
+        let (r, g, b) = if orbit_trap {
+            let t = 1.0 - (-2.0 * min_dist).exp();
+            lut[((t.clamp(0.0, 1.0) * 1023.0) as usize).min(1023)]
+        } else if smooth && iter < max_iter {
+            let nu = if fractal_type == 4 {
+                // Exponential escape (cosine map) breaks the log-log
+                // smoothing estimate; fall back to the raw count.
+                iter as f64
             } else {
-                ((brightness - fade_black) / (255.0 - fade_black)).clamp(0.0, 1.0)
+                let r2 = esc_re * esc_re + esc_im * esc_im;
+                iter as f64 + 1.0 - (0.5 * r2.ln()).ln() / (2.0f64).ln()
             };
-            let alpha = (alpha_norm.powf(alpha_gamma) * 255.0) as u8;
+            lerp_lut(&lut, nu * 10.0)
+        } else {
+            let lut_idx = ((iter as usize).wrapping_mul(10)) % 1024;
+            lut[lut_idx]
+        };
 
-            let idx = (y * w + x) * 4;
+        composite(r, g, b, iter)
+    };
 
-            if transparent {
-                let a = if iter >= max_iter { 0 } else { alpha };
-                rgba[idx]     = r;
-                rgba[idx + 1] = g;
-                rgba[idx + 2] = b;
-                rgba[idx + 3] = a;
-            } else {
-                // Composite over background
-                let a_f = if iter >= max_iter { 0.0 } else { alpha as f64 / 255.0 };
-                rgba[idx]     = (r as f64 * a_f + bg_r as f64 * (1.0 - a_f)) as u8;
-                rgba[idx + 1] = (g as f64 * a_f + bg_g as f64 * (1.0 - a_f)) as u8;
-                rgba[idx + 2] = (b as f64 * a_f + bg_b as f64 * (1.0 - a_f)) as u8;
-                rgba[idx + 3] = 255;
+    let n_samples = (aa * aa) as usize;
+
+    // Renders one output row (banded, smooth and orbit-trap paths) as `w * 4` RGBA bytes. All
+    // captured state (`lut`, bounds, `iterate` fn pointer) is read-only after
+    // setup, so rows are independent and safe to compute on separate threads.
+    let render_row_bands = |y: usize| -> Vec<u8> {
+        let imag_base0 = min_y + (y as f64 / h as f64) * (max_y - min_y);
+        let mut row = vec![0u8; w * 4];
+        for x in 0..w {
+            let real_base0 = min_x + (x as f64 / w as f64) * (max_x - min_x);
+
+            let mut acc = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+            for sy in 0..aa {
+                let jitter_y = (sy as f64 + 0.5) / aa as f64 - 0.5;
+                for sx in 0..aa {
+                    let jitter_x = (sx as f64 + 0.5) / aa as f64 - 0.5;
+                    let (r, g, b, a) = sample(real_base0 + jitter_x * step_x, imag_base0 + jitter_y * step_y);
+                    acc.0 += r;
+                    acc.1 += g;
+                    acc.2 += b;
+                    acc.3 += a;
+                }
+            }
+            let n = n_samples as f64;
+            let idx = x * 4;
+            row[idx]     = (acc.0 / n) as u8;
+            row[idx + 1] = (acc.1 / n) as u8;
+            row[idx + 2] = (acc.2 / n) as u8;
+            row[idx + 3] = (acc.3 / n) as u8;
+        }
+        row
+    };
+
+    if histogram {
+        // Two-pass histogram-equalized coloring: escape counts allocate
+        // palette range by frequency rather than by raw iteration number, so
+        // deep zooms (where most pixels land in a narrow escape-count band)
+        // still use the full gradient. Each pass is row-tiled the same way
+        // as the banded path below.
+        let compute_row_counts = |y: usize| -> Vec<u32> {
+            let imag_base0 = min_y + (y as f64 / h as f64) * (max_y - min_y);
+            let mut row = vec![0u32; w * n_samples];
+            for x in 0..w {
+                let real_base0 = min_x + (x as f64 / w as f64) * (max_x - min_x);
+                for sy in 0..aa {
+                    let jitter_y = (sy as f64 + 0.5) / aa as f64 - 0.5;
+                    for sx in 0..aa {
+                        let jitter_x = (sx as f64 + 0.5) / aa as f64 - 0.5;
+                        let (iter, _, _, _) = escape_at(real_base0 + jitter_x * step_x, imag_base0 + jitter_y * step_y);
+                        row[x * n_samples + (sy * aa + sx) as usize] = iter;
+                    }
+                }
+            }
+            row
+        };
+
+        #[cfg(feature = "parallel")]
+        let count_rows: Vec<Vec<u32>> = (0..h).into_par_iter().map(compute_row_counts).collect();
+        #[cfg(not(feature = "parallel"))]
+        let count_rows: Vec<Vec<u32>> = (0..h).map(compute_row_counts).collect();
+
+        let mut hist = vec![0u64; max_iter as usize + 1];
+        let mut total_escaped: u64 = 0;
+        for row in &count_rows {
+            for &iter in row {
+                if iter < max_iter {
+                    hist[iter as usize] += 1;
+                    total_escaped += 1;
+                }
             }
         }
+
+        let mut cdf = vec![0.0f64; max_iter as usize + 1];
+        let mut running: u64 = 0;
+        for k in 0..=max_iter as usize {
+            running += hist[k];
+            cdf[k] = if total_escaped > 0 { running as f64 / total_escaped as f64 } else { 0.0 };
+        }
+
+        let colorize_row = |counts_row: &Vec<u32>| -> Vec<u8> {
+            let mut row = vec![0u8; w * 4];
+            for x in 0..w {
+                let mut acc = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+                for sy in 0..aa {
+                    for sx in 0..aa {
+                        let iter = counts_row[x * n_samples + (sy * aa + sx) as usize];
+                        let (r, g, b) = if iter >= max_iter {
+                            lut[0]
+                        } else {
+                            let lut_idx = ((cdf[iter as usize] * 1023.0) as usize).min(1023);
+                            lut[lut_idx]
+                        };
+                        let (r, g, b, a) = composite(r, g, b, iter);
+                        acc.0 += r;
+                        acc.1 += g;
+                        acc.2 += b;
+                        acc.3 += a;
+                    }
+                }
+                let n = n_samples as f64;
+                let idx = x * 4;
+                row[idx]     = (acc.0 / n) as u8;
+                row[idx + 1] = (acc.1 / n) as u8;
+                row[idx + 2] = (acc.2 / n) as u8;
+                row[idx + 3] = (acc.3 / n) as u8;
+            }
+            row
+        };
+
+        #[cfg(feature = "parallel")]
+        let rows: Vec<Vec<u8>> = count_rows.par_iter().map(colorize_row).collect();
+        #[cfg(not(feature = "parallel"))]
+        let rows: Vec<Vec<u8>> = count_rows.iter().map(colorize_row).collect();
+
+        let mut rgba = vec![0u8; w * h * 4];
+        for (y, row) in rows.into_iter().enumerate() {
+            rgba[y * w * 4..(y + 1) * w * 4].copy_from_slice(&row);
+        }
+        return rgba;
+    }
+
+    #[cfg(feature = "parallel")]
+    let rows: Vec<Vec<u8>> = (0..h).into_par_iter().map(render_row_bands).collect();
+    #[cfg(not(feature = "parallel"))]
+    let rows: Vec<Vec<u8>> = (0..h).map(render_row_bands).collect();
+
+    let mut rgba = vec![0u8; w * h * 4];
+    for (y, row) in rows.into_iter().enumerate() {
+        rgba[y * w * 4..(y + 1) * w * 4].copy_from_slice(&row);
     }
 
     rgba